@@ -0,0 +1,543 @@
+//! The core bit-stream encoder used when constructing QR codes.
+//!
+//! [`Bits`] accumulates the mode indicators, character-count indicators and
+//! packed data of one or more segments, followed by a terminator and padding
+//! up to the symbol's full data capacity. The result ([`Bits::into_bytes`])
+//! is what `ec::construct_codewords` splits into error-correction blocks.
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+use crate::types::{EcLevel, QrError, QrResult, Version};
+
+/// An under-construction bit stream for a QR code symbol.
+///
+/// Use the `push_*_data` methods to append segments, then
+/// [`Bits::push_terminator`] to close the stream out, and
+/// [`Bits::into_bytes`] to get the packed bytes ready for
+/// `ec::construct_codewords`.
+pub struct Bits {
+    data: Vec<u8>,
+    bit_offset: usize,
+    version: Version,
+}
+
+impl Bits {
+    /// Constructs an empty bit stream for the given `version`.
+    pub fn new(version: Version) -> Self {
+        Self {
+            data: Vec::new(),
+            bit_offset: 0,
+            version,
+        }
+    }
+
+    /// The version this bit stream was constructed for.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// The number of bits pushed so far.
+    pub fn len(&self) -> usize {
+        self.data.len() * 8 - (8 - self.bit_offset) % 8
+    }
+
+    /// Whether any bits have been pushed yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Consumes the bit stream, returning the packed bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Appends the low `n` bits of `bits` (`n <= 32`), most-significant-bit
+    /// first.
+    fn push_bits(&mut self, bits: u32, n: u8) {
+        for i in (0..n).rev() {
+            if self.bit_offset == 0 {
+                self.data.push(0);
+            }
+            if (bits >> i) & 1 == 1 {
+                let last = self.data.last_mut().expect("just pushed a byte above");
+                *last |= 0x80 >> self.bit_offset;
+            }
+            self.bit_offset = (self.bit_offset + 1) % 8;
+        }
+    }
+
+    /// Appends the mode indicator and character-count indicator for `mode`
+    /// encoding `raw_data_len` characters, sized for this stream's version.
+    fn push_header(&mut self, mode: Mode, raw_data_len: usize) -> QrResult<()> {
+        match (self.version, mode) {
+            // M1 holds only numeric data and M2 has no byte mode.
+            (Version::Micro(1), Mode::Alphanumeric | Mode::Byte)
+            | (Version::Micro(2), Mode::Byte) => return Err(QrError::DataTooLong),
+            _ => {}
+        }
+        match self.version {
+            Version::Micro(micro_number) if micro_number > 1 => {
+                self.push_bits(mode.micro_indicator(), micro_number - 1);
+            }
+            Version::Micro(_) => {}
+            Version::Normal(_) => self.push_bits(mode.indicator(), 4),
+        }
+        let count_bits = mode.character_count_bits(self.version);
+        if raw_data_len >= (1 << count_bits) {
+            return Err(QrError::DataTooLong);
+        }
+        self.push_bits(raw_data_len as u32, count_bits);
+        Ok(())
+    }
+
+    /// Appends `data` as a numeric-mode segment: 3 decimal digits packed
+    /// into every 10 bits (2 digits into 7 bits, 1 digit into 4 bits, for a
+    /// trailing partial group).
+    ///
+    /// # Errors
+    ///
+    /// Returns `QrError::InvalidCharacter` if `data` contains a byte outside
+    /// `b'0'..=b'9'`, or `QrError::DataTooLong` if it does not fit.
+    pub fn push_numeric_data(&mut self, data: &[u8]) -> QrResult<()> {
+        if !data.iter().all(u8::is_ascii_digit) {
+            return Err(QrError::InvalidCharacter);
+        }
+        self.push_header(Mode::Numeric, data.len())?;
+        for chunk in data.chunks(3) {
+            let value = chunk
+                .iter()
+                .fold(0_u32, |acc, &b| acc * 10 + u32::from(b - b'0'));
+            let bits = match chunk.len() {
+                3 => 10,
+                2 => 7,
+                1 => 4,
+                _ => unreachable!(),
+            };
+            self.push_bits(value, bits);
+        }
+        Ok(())
+    }
+
+    /// Appends `data` as an alphanumeric-mode segment (digits, uppercase
+    /// letters, space, and `` $%*+-./: ``): 2 characters packed into every
+    /// 11 bits (1 character into 6 bits, for a trailing partial group).
+    ///
+    /// # Errors
+    ///
+    /// Returns `QrError::InvalidCharacter` if `data` contains a byte outside
+    /// the alphanumeric character set, or `QrError::DataTooLong` if it does
+    /// not fit.
+    pub fn push_alphanumeric_data(&mut self, data: &[u8]) -> QrResult<()> {
+        if !data.iter().all(|&b| alphanumeric_value(b).is_some()) {
+            return Err(QrError::InvalidCharacter);
+        }
+        self.push_header(Mode::Alphanumeric, data.len())?;
+        for chunk in data.chunks(2) {
+            match *chunk {
+                [a, b] => {
+                    let value =
+                        u32::from(alphanumeric_value(a).unwrap()) * 45
+                            + u32::from(alphanumeric_value(b).unwrap());
+                    self.push_bits(value, 11);
+                }
+                [a] => self.push_bits(u32::from(alphanumeric_value(a).unwrap()), 6),
+                _ => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends `data` as a byte-mode segment, 8 bits per byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QrError::DataTooLong` if it does not fit.
+    pub fn push_byte_data(&mut self, data: &[u8]) -> QrResult<()> {
+        self.push_header(Mode::Byte, data.len())?;
+        for &b in data {
+            self.push_bits(u32::from(b), 8);
+        }
+        Ok(())
+    }
+
+    /// Appends `data` using whichever single mode (numeric, alphanumeric, or
+    /// byte) packs it most tightly.
+    ///
+    /// This picks one mode for the whole segment; it does not attempt the
+    /// ISO/IEC 18004 optimal multi-segment split.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QrError::DataTooLong` if it does not fit.
+    pub fn push_optimal_data(&mut self, data: &[u8]) -> QrResult<()> {
+        if data.iter().all(u8::is_ascii_digit) {
+            self.push_numeric_data(data)
+        } else if data.iter().all(|&b| alphanumeric_value(b).is_some()) {
+            self.push_alphanumeric_data(data)
+        } else {
+            self.push_byte_data(data)
+        }
+    }
+
+    /// Closes out the bit stream: appends the terminator, pads to a byte
+    /// boundary, then pads whole bytes (alternating `0xEC`/`0x11`) up to the
+    /// symbol's full data capacity at `ec_level`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QrError::DataTooLong` if the data already pushed exceeds the
+    /// symbol's capacity at `ec_level`.
+    pub fn push_terminator(&mut self, ec_level: EcLevel) -> QrResult<()> {
+        let capacity_bits = crate::ec::data_capacity_bits(self.version, ec_level)?;
+        if self.len() > capacity_bits {
+            return Err(QrError::DataTooLong);
+        }
+        let terminator_len = std::cmp::min(4, capacity_bits - self.len());
+        self.push_bits(0, terminator_len as u8);
+        if self.bit_offset != 0 {
+            self.push_bits(0, (8 - self.bit_offset) as u8);
+        }
+        let mut pad_is_ec = true;
+        while self.data.len() * 8 < capacity_bits {
+            self.data.push(if pad_is_ec { 0xEC } else { 0x11 });
+            pad_is_ec = !pad_is_ec;
+        }
+        Ok(())
+    }
+
+    /// Appends a URL prefix followed by a compressed binary blob encoded as a
+    /// *numeric* segment.
+    ///
+    /// The blob is zlib-compressed, the compressed bytes are then treated as
+    /// one big-endian unsigned integer and repeatedly divided by 10 to obtain
+    /// a string of decimal digits. Packing binary data as digits and letting
+    /// the numeric mode's 3-digits-per-10-bits encoding take over is far more
+    /// compact than percent-encoding the same bytes directly into the URL,
+    /// which is what makes this worth doing instead of just appending a byte
+    /// segment.
+    ///
+    /// `url_prefix` is pushed first as a byte segment (the URL up to and
+    /// including the query parameter's `=`, e.g.
+    /// `"https://example.com/d?q="`), followed by the digit string as a
+    /// numeric segment.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QrError::DataTooLong` if the URL prefix and the resulting
+    /// digit string together do not fit in the version this `Bits` was
+    /// created with.
+    pub fn push_compressed_numeric_data(&mut self, url_prefix: &[u8], blob: &[u8]) -> QrResult<()> {
+        let digits = compress_to_decimal_digits(blob)?;
+        self.push_byte_data(url_prefix)?;
+        self.push_numeric_data(&digits)
+    }
+}
+
+/// The three encoding modes `Bits` knows how to pack data into.
+#[derive(Clone, Copy)]
+enum Mode {
+    Numeric,
+    Alphanumeric,
+    Byte,
+}
+
+impl Mode {
+    /// The 4-bit mode indicator used by a non-micro symbol.
+    fn indicator(self) -> u32 {
+        match self {
+            Mode::Numeric => 0b0001,
+            Mode::Alphanumeric => 0b0010,
+            Mode::Byte => 0b0100,
+        }
+    }
+
+    /// The mode indicator used by a micro symbol (`Version::Micro(2..=4)`;
+    /// `Version::Micro(1)` has no mode indicator at all since it only
+    /// supports numeric data).
+    fn micro_indicator(self) -> u32 {
+        match self {
+            Mode::Numeric => 0b00,
+            Mode::Alphanumeric => 0b01,
+            Mode::Byte => 0b10,
+        }
+    }
+
+    /// The width, in bits, of the character-count indicator for this mode at
+    /// `version`.
+    fn character_count_bits(self, version: Version) -> u8 {
+        match (version, self) {
+            (Version::Micro(1), Mode::Numeric) => 3,
+            (Version::Micro(2), Mode::Numeric) => 4,
+            (Version::Micro(2), Mode::Alphanumeric) => 3,
+            (Version::Micro(3), Mode::Numeric) => 5,
+            (Version::Micro(3), Mode::Alphanumeric) => 4,
+            (Version::Micro(3), Mode::Byte) => 4,
+            (Version::Micro(4), Mode::Numeric) => 6,
+            (Version::Micro(4), Mode::Alphanumeric) => 5,
+            (Version::Micro(4), Mode::Byte) => 5,
+            (Version::Micro(_), _) => unreachable!("unsupported micro mode/version combination"),
+            (Version::Normal(1..=9), Mode::Numeric) => 10,
+            (Version::Normal(1..=9), Mode::Alphanumeric) => 9,
+            (Version::Normal(1..=9), Mode::Byte) => 8,
+            (Version::Normal(10..=26), Mode::Numeric) => 12,
+            (Version::Normal(10..=26), Mode::Alphanumeric) => 11,
+            (Version::Normal(10..=26), Mode::Byte) => 16,
+            (Version::Normal(_), Mode::Numeric) => 14,
+            (Version::Normal(_), Mode::Alphanumeric) => 13,
+            (Version::Normal(_), Mode::Byte) => 16,
+        }
+    }
+}
+
+/// The alphanumeric-mode value (`0..45`) of `b`, or `None` if `b` is outside
+/// the alphanumeric character set (digits, uppercase letters, space, and
+/// `` $%*+-./: ``).
+fn alphanumeric_value(b: u8) -> Option<u8> {
+    Some(match b {
+        b'0'..=b'9' => b - b'0',
+        b'A'..=b'Z' => b - b'A' + 10,
+        b' ' => 36,
+        b'$' => 37,
+        b'%' => 38,
+        b'*' => 39,
+        b'+' => 40,
+        b'-' => 41,
+        b'.' => 42,
+        b'/' => 43,
+        b':' => 44,
+        _ => return None,
+    })
+}
+
+/// Zlib-compresses `blob` and converts the compressed bytes into a string of
+/// ASCII decimal digits suitable for a numeric-mode segment.
+///
+/// See [`bytes_to_decimal_digits`] for the conversion itself.
+fn compress_to_decimal_digits(blob: &[u8]) -> QrResult<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder
+        .write_all(blob)
+        .and_then(|()| encoder.finish())
+        .map_err(|_| QrError::InvalidData)
+        .and_then(|compressed| bytes_to_decimal_digits(&compressed))
+}
+
+/// Converts `bytes` into a string of ASCII decimal digits (most-significant
+/// digit first) from which `bytes` can be recovered exactly.
+///
+/// `bytes` is treated as one big-endian unsigned integer and repeatedly
+/// divided by 10 to emit digits. Leading zero *bytes* carry no weight in that
+/// integer and would otherwise be lost, so the digit string is prefixed with
+/// a fixed-width, zero-padded count of how many there were: the first 3
+/// digits are that count, and the rest are the converted value (with no
+/// leading zero digits of its own, other than what the count already
+/// covers). Decoding then reads the count, rebuilds the integer from the
+/// remaining digits, and prepends that many zero bytes to its minimal
+/// big-endian byte representation.
+///
+/// # Errors
+///
+/// Returns `QrError::InvalidData` if `bytes` has more than 999 leading zero
+/// bytes, which the fixed-width count cannot represent.
+fn bytes_to_decimal_digits(bytes: &[u8]) -> QrResult<Vec<u8>> {
+    let leading_zero_bytes = bytes.iter().take_while(|&&b| b == 0).count();
+    if leading_zero_bytes > 999 {
+        return Err(QrError::InvalidData);
+    }
+    let mut number = bytes[leading_zero_bytes..].to_vec();
+    let mut digits = Vec::new();
+    while !number.iter().all(|&b| b == 0) {
+        let remainder = divmod10_in_place(&mut number);
+        digits.push(b'0' + remainder);
+    }
+    digits.reverse();
+    let mut result = format!("{leading_zero_bytes:03}").into_bytes();
+    result.extend(digits);
+    Ok(result)
+}
+
+/// Automatically encodes `data` at the given error correction level, picking
+/// the smallest version (expanding up to `Version::Normal(40)`) that fits.
+///
+/// # Errors
+///
+/// Returns `QrError::DataTooLong` if `data` does not fit at `ec_level` in any
+/// supported version.
+pub fn encode_auto(data: &[u8], ec_level: EcLevel) -> QrResult<Bits> {
+    encode_up_to_version(data, ec_level, Version::Normal(40))
+}
+
+/// Like [`encode_auto`], but never picks a version larger than
+/// `max_version`.
+///
+/// Callers that must render into a fixed display area — where modules become
+/// too small to scan reliably beyond a certain version — need the smallest
+/// version *at or below* a ceiling instead of `encode_auto`'s unconditional
+/// expansion up to V40.
+///
+/// # Errors
+///
+/// Returns `QrError::DataTooLong` if `data` does not fit at `ec_level` in any
+/// version up to and including `max_version`.
+pub fn encode_auto_with_max_version(
+    data: &[u8],
+    ec_level: EcLevel,
+    max_version: Version,
+) -> QrResult<Bits> {
+    encode_up_to_version(data, ec_level, max_version)
+}
+
+/// Automatically encodes a URL prefix followed by a compressed binary blob
+/// (see [`Bits::push_compressed_numeric_data`]), picking the smallest version
+/// at or below `max_version` that fits.
+///
+/// The blob is compressed once up front; only the cheap segment pushes (the
+/// URL prefix and the digit string) are retried for each candidate version.
+///
+/// # Errors
+///
+/// Returns `QrError::DataTooLong` if the encoded payload does not fit at
+/// `ec_level` in any version up to and including `max_version`.
+pub fn encode_compressed_url_up_to_version(
+    url_prefix: &[u8],
+    blob: &[u8],
+    ec_level: EcLevel,
+    max_version: Version,
+) -> QrResult<Bits> {
+    let digits = compress_to_decimal_digits(blob)?;
+    encode_up_to_version_with(ec_level, max_version, |bits| {
+        bits.push_byte_data(url_prefix)?;
+        bits.push_numeric_data(&digits)
+    })
+}
+
+/// Shared implementation behind [`encode_auto`] and
+/// [`encode_auto_with_max_version`]: tries each version up to and including
+/// `max_version`, smallest first, and returns the first one `data` fits in.
+fn encode_up_to_version(data: &[u8], ec_level: EcLevel, max_version: Version) -> QrResult<Bits> {
+    encode_up_to_version_with(ec_level, max_version, |bits| bits.push_optimal_data(data))
+}
+
+/// Tries each version up to and including `max_version`, smallest first,
+/// pushing data into a fresh `Bits` via `push` and returning the first
+/// version it fits in.
+fn encode_up_to_version_with<F>(
+    ec_level: EcLevel,
+    max_version: Version,
+    mut push: F,
+) -> QrResult<Bits>
+where
+    F: FnMut(&mut Bits) -> QrResult<()>,
+{
+    for version in version_sequence_up_to(max_version) {
+        let mut bits = Bits::new(version);
+        if push(&mut bits).is_ok() && bits.push_terminator(ec_level).is_ok() {
+            return Ok(bits);
+        }
+    }
+    Err(QrError::DataTooLong)
+}
+
+/// The sequence of versions `encode_auto` would try, from smallest to
+/// largest, truncated at (and including) `max_version`.
+///
+/// Micro versions are always smaller than Normal ones, so a `Normal` ceiling
+/// still leaves every Micro version in play; a `Micro` ceiling rules out
+/// Normal versions entirely.
+fn version_sequence_up_to(max_version: Version) -> Vec<Version> {
+    match max_version {
+        Version::Micro(m) => (1..=m).map(Version::Micro).collect(),
+        Version::Normal(n) => (1..=4)
+            .map(Version::Micro)
+            .chain((1..=n).map(Version::Normal))
+            .collect(),
+    }
+}
+
+/// Divides the big-endian unsigned integer held in `number` by 10 in place,
+/// shrinking leading zero bytes, and returns the remainder.
+fn divmod10_in_place(number: &mut Vec<u8>) -> u8 {
+    let mut remainder: u16 = 0;
+    for byte in number.iter_mut() {
+        let acc = (remainder << 8) | u16::from(*byte);
+        *byte = (acc / 10) as u8;
+        remainder = acc % 10;
+    }
+    while number.len() > 1 && number[0] == 0 {
+        number.remove(0);
+    }
+    remainder as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bytes_to_decimal_digits;
+
+    /// Reverses `bytes_to_decimal_digits`: reads the 3-digit leading-zero
+    /// count, rebuilds the big integer from the rest by repeated
+    /// multiply-by-10-and-add, then prepends that many zero bytes to its
+    /// minimal big-endian byte representation.
+    fn decimal_digits_to_bytes(digits: &[u8]) -> Vec<u8> {
+        let count_str = std::str::from_utf8(&digits[..3]).unwrap();
+        let leading_zero_bytes: usize = count_str.parse().unwrap();
+
+        let mut number: Vec<u8> = vec![0];
+        for &digit in &digits[3..] {
+            let mut carry = u16::from(digit - b'0');
+            for byte in number.iter_mut().rev() {
+                let acc = u16::from(*byte) * 10 + carry;
+                *byte = acc as u8;
+                carry = acc >> 8;
+            }
+            while carry > 0 {
+                number.insert(0, carry as u8);
+                carry >>= 8;
+            }
+        }
+        while number.len() > 1 && number[0] == 0 {
+            number.remove(0);
+        }
+        if number == [0] {
+            number.clear();
+        }
+
+        let mut bytes = vec![0u8; leading_zero_bytes];
+        bytes.extend(number);
+        bytes
+    }
+
+    fn assert_round_trip(bytes: &[u8]) {
+        let digits = bytes_to_decimal_digits(bytes).unwrap();
+        assert!(digits.iter().all(u8::is_ascii_digit));
+        assert_eq!(decimal_digits_to_bytes(&digits), bytes);
+    }
+
+    #[test]
+    fn round_trips_simple_bytes() {
+        assert_round_trip(&[1, 2, 3]);
+        assert_round_trip(&[0xff, 0x00, 0xab]);
+    }
+
+    #[test]
+    fn round_trips_bytes_with_leading_zeros() {
+        assert_round_trip(&[0, 0, 1, 2, 3]);
+        assert_eq!(
+            bytes_to_decimal_digits(&[0, 0, 1, 2, 3]).unwrap(),
+            b"00266051"
+        );
+    }
+
+    #[test]
+    fn round_trips_all_zero_bytes() {
+        assert_round_trip(&[0, 0, 0, 0]);
+        assert_eq!(bytes_to_decimal_digits(&[0, 0, 0, 0]).unwrap(), b"004");
+    }
+
+    #[test]
+    fn round_trips_empty_input() {
+        assert_round_trip(&[]);
+        assert_eq!(bytes_to_decimal_digits(&[]).unwrap(), b"000");
+    }
+}
@@ -68,6 +68,36 @@ impl QrCode {
         Self::with_bits(bits, ec_level)
     }
 
+    /// Constructs a new QR code which automatically encodes the given data at
+    /// a specific error correction level, without picking a version larger
+    /// than `max_version`.
+    ///
+    /// This is for consumers that must render into a fixed display area,
+    /// where beyond a certain version the modules become too small to scan
+    /// reliably: it finds the smallest version *at or below* `max_version`
+    /// that fits the data, rather than expanding all the way up to V40.
+    ///
+    ///     use qrcode::{QrCode, EcLevel, Version};
+    ///
+    ///     let code = QrCode::with_max_version(
+    ///         b"Some data",
+    ///         EcLevel::M,
+    ///         Version::Normal(10),
+    ///     ).unwrap();
+    ///
+    /// # Errors
+    ///
+    /// Returns `QrError::DataTooLong` if the data does not fit at `ec_level`
+    /// in any version up to and including `max_version`.
+    pub fn with_max_version<D: AsRef<[u8]>>(
+        data: D,
+        ec_level: EcLevel,
+        max_version: Version,
+    ) -> QrResult<Self> {
+        let bits = bits::encode_auto_with_max_version(data.as_ref(), ec_level, max_version)?;
+        Self::with_bits(bits, ec_level)
+    }
+
     /// Constructs a new QR code for the given version and error correction
     /// level.
     ///
@@ -140,6 +170,42 @@ impl QrCode {
         })
     }
 
+    /// Constructs a new QR code that carries a compressed binary blob as a
+    /// numeric-mode query parameter appended to a URL.
+    ///
+    /// The blob is zlib-compressed and converted to a decimal digit string
+    /// (see [`Bits::push_compressed_numeric_data`]), which packs far more
+    /// payload per module than percent-encoding the raw bytes into the URL
+    /// would. This is intended for cases like embedding a few kilobytes of
+    /// arbitrary data behind a URL that a scanner resolves server-side.
+    ///
+    /// Like [`Self::new`], this automatically picks the smallest version that
+    /// fits, expanding up to `Version::Normal(40)` if that's what the
+    /// compressed payload takes.
+    ///
+    ///     use qrcode::{QrCode, EcLevel};
+    ///
+    ///     let code = QrCode::with_compressed_url(
+    ///         "https://example.com/d?q=",
+    ///         b"some binary payload",
+    ///         EcLevel::M,
+    ///     ).unwrap();
+    ///
+    /// # Errors
+    ///
+    /// Returns `QrError::DataTooLong` if the compressed payload, once
+    /// converted to digits, does not fit any version up to and including
+    /// `Version::Normal(40)`.
+    pub fn with_compressed_url(url_prefix: &str, blob: &[u8], ec_level: EcLevel) -> QrResult<Self> {
+        let bits = bits::encode_compressed_url_up_to_version(
+            url_prefix.as_bytes(),
+            blob,
+            ec_level,
+            Version::Normal(40),
+        )?;
+        Self::with_bits(bits, ec_level)
+    }
+
     /// Gets the version of this QR code.
     pub fn version(&self) -> Version {
         self.version
@@ -239,6 +305,28 @@ impl QrCode {
         let quiet_zone = if self.version.is_micro() { 2 } else { 4 };
         Renderer::new(&self.content, self.width, quiet_zone)
     }
+
+    /// Streams every module's coordinates and color directly to `callback`,
+    /// without allocating an intermediate image.
+    ///
+    /// This is the callback-based counterpart to [`Self::render`], for
+    /// environments that cannot pull in the `image` crate or allocate a full
+    /// pixel buffer, such as drawing straight into a raw framebuffer. See
+    /// [`render::bitmap`] for a buffer-filling variant.
+    ///
+    ///     use qrcode::QrCode;
+    ///
+    ///     let code = QrCode::new(b"hello").unwrap();
+    ///     code.render_modules(1, |_x, _y, _dark| {
+    ///         // draw a single pixel
+    ///     });
+    ///
+    /// `scale` is the integer pixel size of a single module; coordinates are
+    /// offset by the code's default quiet zone.
+    pub fn render_modules<F: FnMut(u32, u32, bool)>(&self, scale: u32, callback: F) {
+        let quiet_zone = if self.version.is_micro() { 2 } else { 4 };
+        render::bitmap::render_modules(&self.content, self.width, quiet_zone, scale, callback);
+    }
 }
 
 impl Index<(usize, usize)> for QrCode {
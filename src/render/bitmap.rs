@@ -0,0 +1,198 @@
+//! Allocation-free rendering, parallel to [`svg`](../svg/index.html),
+//! [`unicode`](../unicode/index.html) and the `image`-crate-backed renderer.
+//!
+//! Neither entry point here builds an intermediate image: [`render_modules`]
+//! streams pixel coordinates to a caller's callback, and [`BitmapRenderer`]
+//! writes bits into a caller-provided buffer, which is what makes them usable
+//! in contexts that cannot pull in the `image` crate or allocate a full pixel
+//! buffer, such as a firmware or panic-handler screen writing straight into a
+//! framebuffer.
+
+use crate::types::Color;
+
+/// Calls `callback(x, y, dark)` once for every pixel of a rendered symbol,
+/// including the quiet zone, without allocating: each module yields
+/// `scale * scale` calls.
+///
+/// `(x, y)` are pixel coordinates scaled by `scale` and offset by
+/// `quiet_zone` modules; `dark` is `true` for a dark module and `false` for a
+/// light one (the quiet zone is always light).
+///
+/// # Panics
+///
+/// Panics if `scale` is `0`.
+pub fn render_modules<F: FnMut(u32, u32, bool)>(
+    colors: &[Color],
+    width: usize,
+    quiet_zone: u32,
+    scale: u32,
+    mut callback: F,
+) {
+    assert!(scale > 0, "scale must be at least 1");
+    let width = width as u32;
+    let symbol_width = width + 2 * quiet_zone;
+    for symbol_y in 0..symbol_width {
+        for symbol_x in 0..symbol_width {
+            let dark = if symbol_x < quiet_zone
+                || symbol_y < quiet_zone
+                || symbol_x - quiet_zone >= width
+                || symbol_y - quiet_zone >= width
+            {
+                false
+            } else {
+                let x = (symbol_x - quiet_zone) as usize;
+                let y = (symbol_y - quiet_zone) as usize;
+                colors[y * width as usize + x] != Color::Light
+            };
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    callback(symbol_x * scale + dx, symbol_y * scale + dy, dark);
+                }
+            }
+        }
+    }
+}
+
+/// Renders a symbol into a caller-provided monochrome, row-packed bitmap:
+/// one bit per pixel, most significant bit first, rows padded to a whole
+/// number of bytes.
+///
+/// This performs no allocation: `buffer` must already be at least
+/// [`BitmapRenderer::required_len`] bytes long.
+pub struct BitmapRenderer {
+    scale: u32,
+    quiet_zone: u32,
+    invert: bool,
+}
+
+impl BitmapRenderer {
+    /// Creates a renderer with a 1x scale, a 4-module quiet zone, and no
+    /// inversion.
+    pub fn new() -> Self {
+        Self {
+            scale: 1,
+            quiet_zone: 4,
+            invert: false,
+        }
+    }
+
+    /// Sets the integer pixel scale, i.e. how many bitmap pixels a single
+    /// module occupies per side. Defaults to `1`.
+    pub fn scale(mut self, scale: u32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets the quiet zone width, in modules, added around the symbol.
+    /// Defaults to `4`.
+    pub fn quiet_zone(mut self, quiet_zone: u32) -> Self {
+        self.quiet_zone = quiet_zone;
+        self
+    }
+
+    /// When `true`, dark modules are written as `0` bits and light modules
+    /// (including the quiet zone) as `1` bits, instead of the default.
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// The side length, in pixels, of the bitmap this renderer would produce
+    /// for a symbol of the given module `width`.
+    pub fn pixel_width(&self, width: usize) -> u32 {
+        (width as u32 + 2 * self.quiet_zone) * self.scale
+    }
+
+    /// The number of bytes `buffer` must hold for [`Self::render`] to succeed
+    /// on a symbol of the given module `width`.
+    pub fn required_len(&self, width: usize) -> usize {
+        let pixel_width = self.pixel_width(width) as usize;
+        let stride = (pixel_width + 7) / 8;
+        stride * pixel_width
+    }
+
+    /// Renders `colors` (a `width`-by-`width` grid) into `buffer` as a
+    /// monochrome, row-packed bitmap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer.len()` is shorter than
+    /// [`Self::required_len`]`(width)`.
+    pub fn render(&self, colors: &[Color], width: usize, buffer: &mut [u8]) {
+        let pixel_width = self.pixel_width(width) as usize;
+        let stride = (pixel_width + 7) / 8;
+        assert!(
+            buffer.len() >= stride * pixel_width,
+            "buffer too small for this renderer's configuration"
+        );
+        for b in buffer.iter_mut() {
+            *b = 0;
+        }
+        let invert = self.invert;
+        render_modules(colors, width, self.quiet_zone, self.scale, |x, y, dark| {
+            let bit = dark != invert;
+            if bit {
+                let byte_index = y as usize * stride + x as usize / 8;
+                buffer[byte_index] |= 0x80 >> (x as usize % 8);
+            }
+        });
+    }
+}
+
+impl Default for BitmapRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_modules, BitmapRenderer};
+    use crate::types::Color;
+
+    // A 2x2 symbol, row-major: dark, light / light, dark.
+    const COLORS_2X2: [Color; 4] = [Color::Dark, Color::Light, Color::Light, Color::Dark];
+
+    #[test]
+    fn renders_known_bytes_at_scale_one() {
+        let renderer = BitmapRenderer::new().quiet_zone(0);
+        assert_eq!(renderer.pixel_width(2), 2);
+        assert_eq!(renderer.required_len(2), 2);
+
+        let mut buffer = [0u8; 2];
+        renderer.render(&COLORS_2X2, 2, &mut buffer);
+        // Row 0: dark, light -> bit 7 set.
+        // Row 1: light, dark -> bit 6 set.
+        assert_eq!(buffer, [0b1000_0000, 0b0100_0000]);
+    }
+
+    #[test]
+    fn invert_flips_every_pixel_bit() {
+        let renderer = BitmapRenderer::new().quiet_zone(0).invert(true);
+        let mut buffer = [0u8; 2];
+        renderer.render(&COLORS_2X2, 2, &mut buffer);
+        assert_eq!(buffer, [0b0100_0000, 0b1000_0000]);
+    }
+
+    #[test]
+    fn scale_and_quiet_zone_replicate_and_offset_modules() {
+        let colors = [Color::Dark];
+        let mut pixels = Vec::new();
+        render_modules(&colors, 1, 1, 2, |x, y, dark| pixels.push((x, y, dark)));
+
+        // A 1x1 symbol with a 1-module quiet zone is 3x3 modules, each
+        // replicated 2x2, for a 6x6 pixel grid.
+        assert_eq!(pixels.len(), 36);
+        assert!(!pixels.contains(&(0, 0, true)), "quiet zone must stay light");
+
+        // The single dark module sits at module (1, 1), i.e. pixels (2..4, 2..4).
+        for y in 2..4 {
+            for x in 2..4 {
+                assert!(
+                    pixels.contains(&(x, y, true)),
+                    "expected dark pixel at ({x}, {y})"
+                );
+            }
+        }
+    }
+}